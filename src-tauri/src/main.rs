@@ -12,9 +12,14 @@ async fn main() {
     // Only skip when running via `npm run tauri:dev` (NODE_ENV=development)
     let should_start_server = std::env::var("NODE_ENV").unwrap_or_default() != "development";
 
+    // Kept so the window-close handler can signal the server to shut down
+    // gracefully; `None` when running against an external dev server.
+    let mut shutdown_state: Option<Arc<server::AppState>> = None;
+
     if should_start_server {
         // Initialize server state
         let app_state = Arc::new(server::AppState::new());
+        shutdown_state = Some(app_state.clone());
 
         // Start HTTP + WebSocket server in background
         let server_state = app_state.clone();
@@ -61,9 +66,12 @@ async fn main() {
 
             Ok(())
         })
-        .on_window_event(|_window, event| {
+        .on_window_event(move |_window, event| {
             if let tauri::WindowEvent::CloseRequested { .. } = event {
                 println!("Window closing, server will shutdown gracefully");
+                if let Some(state) = &shutdown_state {
+                    state.shutdown.notify_one();
+                }
             }
         })
         .run(tauri::generate_context!())
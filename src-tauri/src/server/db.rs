@@ -0,0 +1,221 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Row, SqlitePool};
+
+use super::state::{AppState, GameState};
+
+/// One appended board state as returned by the history endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventRow {
+    pub timestamp: DateTime<Utc>,
+    pub state: GameState,
+}
+
+/// Open (creating if needed) the SQLite event store and ensure the schema.
+pub async fn init_pool(path: &str) -> Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true);
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS game_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            room_id TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            state TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_game_events_room_ts ON game_events(room_id, timestamp)")
+        .execute(&pool)
+        .await?;
+
+    Ok(pool)
+}
+
+/// Append an accepted game-state update to the event log.
+pub async fn append_event(
+    pool: &SqlitePool,
+    room_id: &str,
+    state: &GameState,
+) -> Result<(), sqlx::Error> {
+    let json = serde_json::to_string(state)
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    sqlx::query("INSERT INTO game_events (room_id, timestamp, state) VALUES (?, ?, ?)")
+        .bind(room_id)
+        .bind(Utc::now().to_rfc3339())
+        .bind(json)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Full, time-ordered update history for a board.
+pub async fn fetch_history(pool: &SqlitePool, room_id: &str) -> Result<Vec<EventRow>, sqlx::Error> {
+    let rows = sqlx::query("SELECT timestamp, state FROM game_events WHERE room_id = ? ORDER BY timestamp ASC")
+        .bind(room_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().filter_map(parse_row).collect())
+}
+
+/// Reconstruct a board as of `at`: the most recent event at or before it.
+pub async fn state_as_of(
+    pool: &SqlitePool,
+    room_id: &str,
+    at: DateTime<Utc>,
+) -> Result<Option<GameState>, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT timestamp, state FROM game_events
+         WHERE room_id = ? AND timestamp <= ?
+         ORDER BY timestamp DESC LIMIT 1",
+    )
+    .bind(room_id)
+    .bind(at.to_rfc3339())
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.and_then(parse_row).map(|e| e.state))
+}
+
+/// Load the most recent snapshot per room into `AppState` on startup.
+pub async fn load_latest_snapshots(
+    pool: &SqlitePool,
+    state: &Arc<AppState>,
+) -> Result<(), sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT room_id, state FROM game_events
+         WHERE id IN (SELECT MAX(id) FROM game_events GROUP BY room_id)",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in rows {
+        let room_id: String = row.get("room_id");
+        let json: String = row.get("state");
+        if let Ok(game_state) = serde_json::from_str::<GameState>(&json) {
+            let mut rooms = state.rooms.write();
+            let room = rooms.entry(room_id).or_default();
+            room.store_state(&game_state);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse a `(timestamp, state)` row into an [`EventRow`], dropping malformed ones.
+fn parse_row(row: sqlx::sqlite::SqliteRow) -> Option<EventRow> {
+    let ts: String = row.get("timestamp");
+    let json: String = row.get("state");
+    let timestamp = DateTime::parse_from_rfc3339(&ts).ok()?.with_timezone(&Utc);
+    let state = serde_json::from_str::<GameState>(&json).ok()?;
+    Some(EventRow { timestamp, state })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A distinct on-disk database per test so parallel runs don't collide
+    /// (an in-memory pool would give each connection its own empty database).
+    fn temp_db_path(tag: &str) -> String {
+        let mut path = std::env::temp_dir();
+        path.push(format!("bcb_db_test_{}_{}.db", std::process::id(), tag));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn state_with_score(score_top: i32) -> GameState {
+        GameState {
+            game_inning: 3,
+            top: true,
+            first_base: false,
+            second_base: false,
+            third_base: false,
+            ball_cnt: 0,
+            strike_cnt: 0,
+            out_cnt: 0,
+            score_top,
+            score_bottom: 0,
+            game_title: "テスト".to_string(),
+            team_top: "A".to_string(),
+            team_bottom: "B".to_string(),
+            last_inning: 9,
+        }
+    }
+
+    /// Insert a row with an explicit timestamp so history ordering and
+    /// `state_as_of` cut-offs are deterministic. The timestamp is stored in
+    /// the same RFC 3339 form `append_event` writes.
+    async fn insert_at(pool: &SqlitePool, room_id: &str, ts: &str, state: &GameState) {
+        let json = serde_json::to_string(state).unwrap();
+        let ts = DateTime::parse_from_rfc3339(ts).unwrap().with_timezone(&Utc).to_rfc3339();
+        sqlx::query("INSERT INTO game_events (room_id, timestamp, state) VALUES (?, ?, ?)")
+            .bind(room_id)
+            .bind(ts)
+            .bind(json)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_history_is_ordered_and_room_scoped() {
+        let path = temp_db_path("history");
+        let pool = init_pool(&path).await.unwrap();
+
+        insert_at(&pool, "a", "2024-07-01T10:00:00Z", &state_with_score(0)).await;
+        insert_at(&pool, "a", "2024-07-01T10:05:00Z", &state_with_score(1)).await;
+        insert_at(&pool, "b", "2024-07-01T10:02:00Z", &state_with_score(7)).await;
+
+        let history = fetch_history(&pool, "a").await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].state.score_top, 0);
+        assert_eq!(history[1].state.score_top, 1);
+
+        // Room "b" is isolated from room "a".
+        let other = fetch_history(&pool, "b").await.unwrap();
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].state.score_top, 7);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_state_as_of_returns_latest_at_or_before() {
+        let path = temp_db_path("state_as_of");
+        let pool = init_pool(&path).await.unwrap();
+
+        insert_at(&pool, "a", "2024-07-01T10:00:00Z", &state_with_score(0)).await;
+        insert_at(&pool, "a", "2024-07-01T10:05:00Z", &state_with_score(1)).await;
+        insert_at(&pool, "a", "2024-07-01T10:10:00Z", &state_with_score(2)).await;
+
+        let parse = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        // Exactly on an event timestamp returns that event.
+        let at_mid = state_as_of(&pool, "a", parse("2024-07-01T10:05:00Z")).await.unwrap();
+        assert_eq!(at_mid.unwrap().score_top, 1);
+
+        // Between events returns the most recent earlier one.
+        let between = state_as_of(&pool, "a", parse("2024-07-01T10:07:00Z")).await.unwrap();
+        assert_eq!(between.unwrap().score_top, 1);
+
+        // Before the first event there is nothing.
+        let before = state_as_of(&pool, "a", parse("2024-07-01T09:00:00Z")).await.unwrap();
+        assert!(before.is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -5,12 +5,35 @@ use axum::{
 use futures::{SinkExt, StreamExt};
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
+use std::time::{Duration as StdDuration, Instant};
+use parking_lot::Mutex;
 use chrono::{Utc, Duration};
+use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::mpsc::error::TrySendError;
 
 use crate::server::{AppState, Message, ClientRole};
-use crate::server::state::{ClientInfo, TokenGracePeriod};
+use crate::server::state::{ClientInfo, GameRoom, RoomId, TokenGracePeriod, CLIENT_CHANNEL_CAPACITY, DEFAULT_ROOM};
 use crate::server::persistence;
 
+/// How often to send a WebSocket Ping to an idle connection.
+const HEARTBEAT_INTERVAL: StdDuration = StdDuration::from_secs(15);
+/// Drop a connection that produces no traffic (Text/Ping/Pong) for this long.
+const HEARTBEAT_TIMEOUT: StdDuration = StdDuration::from_secs(45);
+
+/// Queue an already-serialized message to a client. Returns `true` when it was
+/// queued and `false` when the client should be evicted (its bounded queue is
+/// full or closed).
+fn try_send_text(sender: &Sender<WsMessage>, json: String) -> bool {
+    match sender.try_send(WsMessage::Text(json)) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            // Too far behind to catch up; signal the caller to drop it.
+            false
+        }
+        Err(TrySendError::Closed(_)) => false,
+    }
+}
+
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
@@ -20,46 +43,110 @@ pub async fn websocket_handler(
 
 async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    // Bounded queue: a stalled viewer fills it and is dropped rather than
+    // letting broadcasts accumulate unbounded in memory.
+    let (tx, mut rx) = mpsc::channel::<WsMessage>(CLIENT_CHANNEL_CAPACITY);
 
     let client_id = state.client_counter.fetch_add(1, Ordering::SeqCst);
+    state.metrics.connected_total.inc();
     println!("New WebSocket connection: client_id={}", client_id);
 
     // Spawn task to send messages from channel to WebSocket
     let send_task = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
-            if sender.send(WsMessage::Text(msg)).await.is_err() {
+            if sender.send(msg).await.is_err() {
                 break;
             }
         }
     });
 
-    // Handle incoming messages
-    while let Some(msg) = receiver.next().await {
-        if let Ok(WsMessage::Text(text)) = msg {
-            if let Ok(message) = serde_json::from_str::<Message>(&text) {
-                handle_message(client_id, message, &state, &tx).await;
-            } else {
-                eprintln!("Failed to parse message from client {}: {}", client_id, text);
+    // Announce the protocol version and supported capabilities before the
+    // client handshakes, so it can feature-detect rather than assume a fixed
+    // protocol.
+    if let Ok(json) = serde_json::to_string(&server_info()) {
+        let _ = tx.try_send(WsMessage::Text(json));
+    }
+
+    // Last time any frame arrived from the client; used to evict dead sockets.
+    let last_seen = Arc::new(Mutex::new(Instant::now()));
+
+    // Heartbeat task: ping idle connections and abort if they go silent.
+    let mut heartbeat = {
+        let hb_tx = tx.clone();
+        let hb_last = last_seen.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(HEARTBEAT_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if hb_last.lock().elapsed() > HEARTBEAT_TIMEOUT {
+                    break;
+                }
+                if hb_tx.send(WsMessage::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+        })
+    };
+
+    // Handle incoming messages, tearing down if the heartbeat task exits.
+    loop {
+        tokio::select! {
+            maybe_msg = receiver.next() => {
+                let Some(msg) = maybe_msg else { break };
+                match msg {
+                    Ok(WsMessage::Text(text)) => {
+                        *last_seen.lock() = Instant::now();
+                        if let Ok(message) = serde_json::from_str::<Message>(&text) {
+                            handle_message(client_id, message, &state, &tx).await;
+                        } else {
+                            eprintln!("Failed to parse message from client {}: {}", client_id, text);
+                        }
+                    }
+                    Ok(WsMessage::Pong(_)) | Ok(WsMessage::Ping(_)) => {
+                        *last_seen.lock() = Instant::now();
+                    }
+                    Ok(WsMessage::Close(_)) | Err(_) => break,
+                    Ok(_) => {}
+                }
+            }
+            _ = &mut heartbeat => {
+                println!("Client {} heartbeat timed out", client_id);
+                break;
             }
         }
     }
 
     // Cleanup on disconnect
     println!("Client {} disconnected", client_id);
+    state.metrics.connected_total.dec();
     handle_disconnect(client_id, &state).await;
     send_task.abort();
+    heartbeat.abort();
+}
+
+/// The capability announcement sent to every client on connect.
+fn server_info() -> Message {
+    Message::ServerInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        capabilities: vec![
+            "delta-updates".to_string(),
+            "rooms".to_string(),
+            "auth".to_string(),
+            "undo-redo".to_string(),
+            "history".to_string(),
+        ],
+    }
 }
 
 async fn handle_message(
     client_id: u64,
     message: Message,
     state: &Arc<AppState>,
-    tx: &tokio::sync::mpsc::UnboundedSender<String>,
+    tx: &Sender<WsMessage>,
 ) {
     match message {
-        Message::Handshake { client_type, master_token } => {
-            handle_handshake(client_id, client_type, master_token, state, tx).await;
+        Message::Handshake { client_type, master_token, room_id, credential } => {
+            handle_handshake(client_id, client_type, master_token, room_id, credential, state, tx).await;
         }
         Message::GameStateUpdate { board_data } => {
             handle_game_state_update(client_id, board_data, state).await;
@@ -67,99 +154,191 @@ async fn handle_message(
         Message::ReleaseMaster => {
             handle_release_master(client_id, state).await;
         }
+        Message::RequestResync => {
+            handle_resync(client_id, state, tx).await;
+        }
+        Message::Undo => {
+            handle_undo_redo(client_id, state, Step::Undo).await;
+        }
+        Message::Redo => {
+            handle_undo_redo(client_id, state, Step::Redo).await;
+        }
         _ => {
             eprintln!("Unexpected message type from client {}", client_id);
         }
     }
 }
 
+/// Persist the current per-room master tokens in the background so a server
+/// restart can restore operator authority.
+fn persist_master_tokens(state: &Arc<AppState>) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = persistence::save_master_tokens(&state).await {
+            eprintln!("Failed to persist master tokens: {}", e);
+        }
+    });
+}
+
+/// Locate the room a connected client currently belongs to.
+fn room_of(state: &Arc<AppState>, client_id: u64) -> Option<RoomId> {
+    state
+        .rooms
+        .read()
+        .iter()
+        .find(|(_, room)| room.clients.contains_key(&client_id))
+        .map(|(id, _)| id.clone())
+}
+
 async fn handle_handshake(
     client_id: u64,
     client_type: String,
     master_token: Option<String>,
+    room_id: Option<RoomId>,
+    credential: Option<String>,
     state: &Arc<AppState>,
-    tx: &tokio::sync::mpsc::UnboundedSender<String>,
+    tx: &Sender<WsMessage>,
 ) {
-    println!("Handshake from client {} (type: {})", client_id, client_type);
+    let room_id = room_id.unwrap_or_else(|| DEFAULT_ROOM.to_string());
+    println!(
+        "Handshake from client {} (type: {}, room: {})",
+        client_id, client_type, room_id
+    );
+
+    // Resolve the role and register the client inside its room.
+    let mut auth_failed = false;
+    let (role, master_client_id, response_token, game_state, version) = {
+        let mut rooms = state.rooms.write();
+        let room = rooms.entry(room_id.clone()).or_insert_with(GameRoom::new);
+
+        let role = if client_type == "operation" {
+            // A valid grace-period reconnection proves prior authority and
+            // bypasses the secret; otherwise the operator secret is required.
+            let reconnecting = master_token
+                .as_deref()
+                .is_some_and(|t| is_grace_reconnect(room, t));
+            if reconnecting || operator_authorized(state, credential.as_deref()) {
+                determine_operation_role(client_id, master_token, room)
+            } else {
+                eprintln!("Client {} failed operator authentication", client_id);
+                auth_failed = true;
+                ClientRole::Viewer
+            }
+        } else {
+            ClientRole::Viewer
+        };
 
-    // Determine client role
-    let role = if client_type == "operation" {
-        determine_operation_role(client_id, master_token, state).await
-    } else {
-        ClientRole::Viewer
-    };
+        room.clients.insert(
+            client_id,
+            ClientInfo {
+                id: client_id,
+                role: role.clone(),
+                client_type: client_type.clone(),
+                room_id: room_id.clone(),
+                connected_at: Utc::now(),
+                sender: tx.clone(),
+            },
+        );
+
+        let response_token = if role == ClientRole::Master {
+            room.master_token.clone()
+        } else {
+            None
+        };
 
-    // Store client info
-    let client_info = ClientInfo {
-        id: client_id,
-        role: role.clone(),
-        client_type: client_type.clone(),
-        connected_at: Utc::now(),
-        sender: tx.clone(),
+        (role, room.master_client_id, response_token, room.load_state(), room.version)
     };
-
-    state.clients.write().insert(client_id, client_info);
+    state.metrics.track_role(&role, 1);
+    if role == ClientRole::Master {
+        persist_master_tokens(state);
+    }
 
     // Send role assignment
-    let master_client_id = state.master_client_id.read().clone();
-    let response_token = if role == ClientRole::Master {
-        state.master_token.read().clone()
-    } else {
-        None
-    };
-
     let response = Message::RoleAssignment {
         role: role.clone(),
         client_id,
         master_client_id,
         master_token: response_token,
+        room_id: room_id.clone(),
     };
 
     if let Ok(json) = serde_json::to_string(&response) {
-        let _ = tx.send(json);
+        let _ = try_send_text(tx, json);
     }
 
-    // Send current game state to new client
-    if let Some(game_state) = state.game_state.read().clone() {
+    // A freshly synced client always gets the full board (plus its version) so
+    // it has a complete baseline to apply later deltas against.
+    if let Some(game_state) = game_state {
         let state_msg = Message::GameStateBroadcast {
             board_data: game_state,
+            version,
         };
         if let Ok(json) = serde_json::to_string(&state_msg) {
-            let _ = tx.send(json);
+            let _ = try_send_text(tx, json);
         }
     }
 
-    println!("Client {} assigned role: {:?}", client_id, role);
+    // Tell a rejected operator why they were downgraded to a viewer.
+    if auth_failed {
+        let err = Message::ErrorResponse {
+            message: "invalid operator credential".to_string(),
+        };
+        if let Ok(json) = serde_json::to_string(&err) {
+            let _ = try_send_text(tx, json);
+        }
+    }
+
+    println!("Client {} assigned role: {:?} (room: {})", client_id, role, room_id);
+}
+
+/// Whether `token` matches the room's still-valid master grace-period token,
+/// i.e. the client is reclaiming authority it held moments ago.
+fn is_grace_reconnect(room: &GameRoom, token: &str) -> bool {
+    room.master_token_grace
+        .as_ref()
+        .is_some_and(|grace| grace.token == token && Utc::now() < grace.expires_at)
 }
 
-async fn determine_operation_role(
+/// Whether a fresh operator may claim Master/Slave. Auth is disabled (always
+/// allowed) when no operator secret is configured; otherwise the supplied
+/// credential must hash to the configured value.
+fn operator_authorized(state: &Arc<AppState>, credential: Option<&str>) -> bool {
+    match &state.operator_secret_hash {
+        None => true,
+        Some(expected) => credential
+            .map(crate::server::state::sha3_hex)
+            .is_some_and(|hash| &hash == expected),
+    }
+}
+
+/// Decide whether an operation client becomes the room's master or a slave,
+/// honouring the grace-period reconnection token. Runs with the room locked.
+fn determine_operation_role(
     client_id: u64,
     provided_token: Option<String>,
-    state: &Arc<AppState>,
+    room: &mut GameRoom,
 ) -> ClientRole {
     // Check if there's a grace period token and it matches
     if let Some(provided_token) = &provided_token {
-        let grace_period = state.master_token_grace.read().clone();
-        if let Some(grace) = grace_period {
+        if let Some(grace) = room.master_token_grace.clone() {
             if &grace.token == provided_token && Utc::now() < grace.expires_at {
                 // Restore master role within grace period
                 println!("Restoring master role for reconnection (token: {})", provided_token);
-                *state.master_client_id.write() = Some(client_id);
-                *state.master_token.write() = Some(provided_token.clone());
-                *state.master_token_grace.write() = None; // Clear grace period
+                room.master_client_id = Some(client_id);
+                room.master_token = Some(provided_token.clone());
+                room.master_token_grace = None; // Clear grace period
                 return ClientRole::Master;
             }
         }
     }
 
     // Check if there's already a master
-    let current_master = state.master_client_id.read().clone();
-    if current_master.is_none() {
+    if room.master_client_id.is_none() {
         // No master exists, assign this client as master
         let new_token = AppState::generate_master_token();
-        *state.master_client_id.write() = Some(client_id);
-        *state.master_token.write() = Some(new_token);
-        *state.master_token_grace.write() = None;
+        room.master_client_id = Some(client_id);
+        room.master_token = Some(new_token);
+        room.master_token_grace = None;
         ClientRole::Master
     } else {
         // Master exists, assign as slave
@@ -172,165 +351,391 @@ async fn handle_game_state_update(
     board_data: crate::server::state::GameState,
     state: &Arc<AppState>,
 ) {
-    // Verify client is master
-    let master_id = state.master_client_id.read().clone();
-    if master_id != Some(client_id) {
-        eprintln!("Client {} attempted to update game state but is not master", client_id);
-        return;
+    let room_id = match room_of(state, client_id) {
+        Some(id) => id,
+        None => {
+            eprintln!("Client {} sent game state update before joining a room", client_id);
+            return;
+        }
+    };
+
+    // Verify client is master of its room
+    {
+        let rooms = state.rooms.read();
+        let master_id = rooms.get(&room_id).and_then(|r| r.master_client_id);
+        if master_id != Some(client_id) {
+            eprintln!("Client {} attempted to update game state but is not master", client_id);
+            return;
+        }
     }
 
-    println!("Updating game state from client {}", client_id);
+    println!("Updating game state from client {} (room: {})", client_id, room_id);
 
-    // Update game state
-    *state.game_state.write() = Some(board_data.clone());
+    // A master re-sending the current board is a no-op: don't advance the
+    // version, append to the journal, or persist a duplicate, or synced clients
+    // would see a version gap and be forced into a spurious resync.
+    let previous = {
+        let rooms = state.rooms.read();
+        rooms.get(&room_id).and_then(|r| r.load_state())
+    };
+    if let Some(prev) = &previous {
+        if crate::server::state::GameStateDelta::between(prev, &board_data).is_empty() {
+            println!("Ignoring no-op game state update from client {}", client_id);
+            return;
+        }
+    }
+
+    // Update game state and append to the journal. A fresh update after an
+    // undo truncates any redo-able entries ahead of the cursor so the journal
+    // stays linear.
+    state.metrics.game_state_updates.inc();
+    state.metrics.current_inning.set(board_data.game_inning as i64);
+    let (truncated, version) = {
+        let mut rooms = state.rooms.write();
+        if let Some(room) = rooms.get_mut(&room_id) {
+            room.store_state(&board_data);
+            room.version += 1;
+            let truncated = room.push_journal(&board_data);
+            (truncated, room.version)
+        } else {
+            (false, 0)
+        }
+    };
 
-    // Save to disk asynchronously
+    // Persist snapshot + journal asynchronously.
     let state_clone = state.clone();
+    let room_for_save = room_id.clone();
+    let board_for_save = board_data.clone();
     tokio::spawn(async move {
-        if let Err(e) = persistence::save_game_state(&state_clone).await {
+        if let Err(e) = persistence::save_game_state(&state_clone, &room_for_save).await {
             eprintln!("Failed to save game state: {}", e);
         }
+        // When the redo tail was dropped, rewrite the journal file so it stays
+        // linear; otherwise a plain append is enough.
+        let result = if truncated {
+            let states = state_clone
+                .rooms
+                .read()
+                .get(&room_for_save)
+                .map(|r| r.journal.clone())
+                .unwrap_or_default();
+            persistence::rewrite_journal(&room_for_save, &states).await
+        } else {
+            persistence::append_journal(&room_for_save, &board_for_save).await
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to append journal: {}", e);
+        }
+        // Append to the SQLite event log for replay/history queries.
+        if let Some(pool) = state_clone.db.get() {
+            if let Err(e) = crate::server::db::append_event(pool, &room_for_save, &board_for_save).await {
+                eprintln!("Failed to append event to SQLite: {}", e);
+            }
+        }
     });
 
-    // Broadcast to all clients
-    broadcast_game_state(state, board_data).await;
+    // Publish to read-only viewers (the room's SSE feed); ignore if none.
+    let _ = state.state_tx.send((room_id.clone(), board_data.clone()));
+
+    // Already-synced clients get only what changed. The very first update of a
+    // room has no prior state to diff against, so fall back to the full board.
+    match previous {
+        Some(prev) => {
+            let delta = crate::server::state::GameStateDelta::between(&prev, &board_data);
+            broadcast_delta(state, &room_id, delta, version).await;
+        }
+        None => broadcast_game_state(state, &room_id, board_data, version).await,
+    }
 }
 
 async fn broadcast_game_state(
     state: &Arc<AppState>,
+    room_id: &str,
     board_data: crate::server::state::GameState,
+    version: u64,
 ) {
-    let message = Message::GameStateBroadcast { board_data };
+    let message = Message::GameStateBroadcast { board_data, version };
+    broadcast_message(state, room_id, &message).await;
+}
 
-    if let Ok(json) = serde_json::to_string(&message) {
-        let clients = state.clients.read();
-        let client_count = clients.len();
-        println!("Broadcasting game state to {} clients", client_count);
+/// Broadcast a field-level delta to every client in the room.
+async fn broadcast_delta(
+    state: &Arc<AppState>,
+    room_id: &str,
+    changes: crate::server::state::GameStateDelta,
+    version: u64,
+) {
+    let message = Message::GameStateDelta { changes, version };
+    broadcast_message(state, room_id, &message).await;
+}
 
-        for (id, client_info) in clients.iter() {
-            println!("  Sending to client {} (role: {:?})", id, client_info.role);
-            if client_info.sender.send(json.clone()).is_err() {
-                eprintln!("Failed to send game state to client {}", id);
-            } else {
-                println!("  Successfully sent to client {}", id);
+/// Serialize `message` once and fan it out to every client in the room,
+/// evicting any that can't keep up. Counts as one broadcast for metrics.
+async fn broadcast_message(state: &Arc<AppState>, room_id: &str, message: &Message) {
+    if let Ok(json) = serde_json::to_string(message) {
+        state.metrics.broadcasts.inc();
+        // Collect clients that can't keep up while holding only the read lock,
+        // then evict them afterwards so we don't upgrade to a write lock here.
+        let mut too_slow = Vec::new();
+        {
+            let rooms = state.rooms.read();
+            let Some(room) = rooms.get(room_id) else { return };
+            println!(
+                "Broadcasting to {} clients (room: {})",
+                room.clients.len(),
+                room_id
+            );
+
+            for (id, client_info) in room.clients.iter() {
+                if !try_send_text(&client_info.sender, json.clone()) {
+                    eprintln!("Client {} too far behind, evicting", id);
+                    too_slow.push(*id);
+                }
             }
         }
+
+        for id in too_slow {
+            handle_disconnect(id, state).await;
+        }
     }
 }
 
-async fn handle_release_master(client_id: u64, state: &Arc<AppState>) {
-    // Verify client is master
-    let master_id = state.master_client_id.read().clone();
-    if master_id != Some(client_id) {
-        eprintln!("Client {} attempted to release master but is not master", client_id);
-        return;
+/// Send the requesting client the full current board so it can recover from a
+/// detected version gap.
+async fn handle_resync(client_id: u64, state: &Arc<AppState>, tx: &Sender<WsMessage>) {
+    let room_id = match room_of(state, client_id) {
+        Some(id) => id,
+        None => return,
+    };
+
+    let snapshot = {
+        let rooms = state.rooms.read();
+        rooms
+            .get(&room_id)
+            .and_then(|room| room.load_state().map(|gs| (gs, room.version)))
+    };
+
+    if let Some((board_data, version)) = snapshot {
+        let message = Message::GameStateBroadcast { board_data, version };
+        if let Ok(json) = serde_json::to_string(&message) {
+            let _ = try_send_text(tx, json);
+        }
     }
+}
 
-    println!("Client {} releasing master authority", client_id);
+/// Direction of a journal cursor move.
+enum Step {
+    Undo,
+    Redo,
+}
 
-    // Clear master
-    *state.master_client_id.write() = None;
-    *state.master_token.write() = None;
-    *state.master_token_grace.write() = None;
+/// Move a room's journal cursor (master only), restore that board state and
+/// re-broadcast it to every client in the room.
+async fn handle_undo_redo(client_id: u64, state: &Arc<AppState>, step: Step) {
+    let room_id = match room_of(state, client_id) {
+        Some(id) => id,
+        None => return,
+    };
+
+    let restored = {
+        let mut rooms = state.rooms.write();
+        let Some(room) = rooms.get_mut(&room_id) else { return };
+
+        // Only the master may correct the score.
+        if room.master_client_id != Some(client_id) {
+            eprintln!("Client {} attempted undo/redo but is not master", client_id);
+            return;
+        }
+
+        let moved = match step {
+            Step::Undo => room.undo(),
+            Step::Redo => room.redo(),
+        };
+        let Some(state_at_cursor) = moved else {
+            println!("Nothing to undo/redo (room: {})", room_id);
+            return;
+        };
+        room.store_state(&state_at_cursor);
+        room.version += 1;
+        (state_at_cursor, room.version)
+    };
+
+    // An undo/redo can move any subset of fields, so re-broadcast the full
+    // board rather than a delta.
+    let (restored, version) = restored;
+    broadcast_game_state(state, &room_id, restored, version).await;
+}
+
+async fn handle_release_master(client_id: u64, state: &Arc<AppState>) {
+    let room_id = match room_of(state, client_id) {
+        Some(id) => id,
+        None => return,
+    };
 
-    // Change former master to slave (exclude from promotion)
     {
-        let mut clients = state.clients.write();
-        if let Some(client_info) = clients.get_mut(&client_id) {
+        let mut rooms = state.rooms.write();
+        let Some(room) = rooms.get_mut(&room_id) else { return };
+
+        // Verify client is master
+        if room.master_client_id != Some(client_id) {
+            eprintln!("Client {} attempted to release master but is not master", client_id);
+            return;
+        }
+
+        println!("Client {} releasing master authority (room: {})", client_id, room_id);
+        state.metrics.master_rotations.inc();
+
+        // Clear master
+        room.master_client_id = None;
+        room.master_token = None;
+        room.master_token_grace = None;
+
+        // Change former master to slave (exclude from promotion)
+        if let Some(client_info) = room.clients.get_mut(&client_id) {
             client_info.role = ClientRole::Slave;
+            state.metrics.track_role(&ClientRole::Master, -1);
+            state.metrics.track_role(&ClientRole::Slave, 1);
         }
     }
 
+    persist_master_tokens(state);
+
     // Promote next slave to master
-    promote_next_slave(state).await;
+    promote_next_slave(state, &room_id).await;
 }
 
 async fn handle_disconnect(client_id: u64, state: &Arc<AppState>) {
-    // Remove client from clients map
-    let removed_client = state.clients.write().remove(&client_id);
+    let room_id = match room_of(state, client_id) {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Remove client from its room
+    let (removed_client, old_token) = {
+        let mut rooms = state.rooms.write();
+        let Some(room) = rooms.get_mut(&room_id) else { return };
+        let removed = room.clients.remove(&client_id);
+        (removed, room.master_token.clone())
+    };
 
     if let Some(client_info) = removed_client {
-        println!("Removed client {} (role: {:?})", client_id, client_info.role);
+        println!("Removed client {} (role: {:?}, room: {})", client_id, client_info.role, room_id);
+        state.metrics.track_role(&client_info.role, -1);
 
         // Check if disconnected client was master
-        let master_id = state.master_client_id.read().clone();
-        if master_id == Some(client_id) {
+        let was_master = {
+            let rooms = state.rooms.read();
+            rooms.get(&room_id).and_then(|r| r.master_client_id) == Some(client_id)
+        };
+        if was_master {
             println!("Master client {} disconnected, starting grace period", client_id);
 
             // Start 5-second grace period
-            let old_token = state.master_token.read().clone();
             if let Some(token) = old_token {
                 let grace_period = TokenGracePeriod {
                     token,
                     expires_at: Utc::now() + Duration::seconds(5),
                 };
-                *state.master_token_grace.write() = Some(grace_period);
+                if let Some(room) = state.rooms.write().get_mut(&room_id) {
+                    room.master_token_grace = Some(grace_period);
+                }
+                persist_master_tokens(state);
 
                 // Schedule promotion after grace period
                 let state_clone = state.clone();
+                let room_for_grace = room_id.clone();
                 tokio::spawn(async move {
                     tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                    promote_if_grace_expired(&state_clone).await;
+                    promote_if_grace_expired(&state_clone, &room_for_grace).await;
                 });
             }
+        } else {
+            // A non-master left; if the room is now empty and has no pending
+            // reconnection, drop it from the registry.
+            drop_room_if_empty(state, &room_id);
+        }
+    }
+}
+
+/// Remove a room from the registry once it has no clients and no master
+/// waiting to reconnect, so idle rooms don't accumulate in memory.
+fn drop_room_if_empty(state: &Arc<AppState>, room_id: &str) {
+    let mut rooms = state.rooms.write();
+    if let Some(room) = rooms.get(room_id) {
+        if room.clients.is_empty() && room.master_token_grace.is_none() {
+            rooms.remove(room_id);
+            println!("Dropped empty room {}", room_id);
         }
     }
 }
 
-async fn promote_if_grace_expired(state: &Arc<AppState>) {
+async fn promote_if_grace_expired(state: &Arc<AppState>, room_id: &str) {
     // Check if grace period has expired
-    let grace_period = state.master_token_grace.read().clone();
-    if let Some(grace) = grace_period {
-        if Utc::now() >= grace.expires_at {
-            println!("Grace period expired, promoting next slave");
-            *state.master_token_grace.write() = None;
-            promote_next_slave(state).await;
+    let expired = {
+        let rooms = state.rooms.read();
+        match rooms.get(room_id).and_then(|r| r.master_token_grace.clone()) {
+            Some(grace) => Utc::now() >= grace.expires_at,
+            None => false,
         }
+    };
+
+    if expired {
+        println!("Grace period expired, promoting next slave (room: {})", room_id);
+        state.metrics.grace_expirations.inc();
+        state.metrics.master_rotations.inc();
+        if let Some(room) = state.rooms.write().get_mut(room_id) {
+            room.master_token_grace = None;
+        }
+        promote_next_slave(state, room_id).await;
     }
 }
 
-async fn promote_next_slave(state: &Arc<AppState>) {
-    let clients = state.clients.read();
+async fn promote_next_slave(state: &Arc<AppState>, room_id: &str) {
+    let mut rooms = state.rooms.write();
+    let Some(room) = rooms.get_mut(room_id) else { return };
 
     // Find the oldest slave (operation type)
-    let oldest_slave = clients
+    let oldest_slave = room
+        .clients
         .values()
         .filter(|c| c.role == ClientRole::Slave && c.client_type == "operation")
-        .min_by_key(|c| c.connected_at);
+        .min_by_key(|c| c.connected_at)
+        .map(|c| c.id);
 
-    if let Some(slave) = oldest_slave {
-        let new_master_id = slave.id;
+    if let Some(new_master_id) = oldest_slave {
         let new_token = AppState::generate_master_token();
 
-        println!("Promoting slave {} to master", new_master_id);
-
-        // Update state
-        drop(clients); // Release read lock
-        *state.master_client_id.write() = Some(new_master_id);
-        *state.master_token.write() = Some(new_token.clone());
-
-        // Update client role
-        {
-            let mut clients = state.clients.write();
-            if let Some(client_info) = clients.get_mut(&new_master_id) {
-                client_info.role = ClientRole::Master;
-
-                // Send role changed message
-                let message = Message::RoleChanged {
-                    new_role: ClientRole::Master,
-                    client_id: new_master_id,
-                    master_client_id: Some(new_master_id),
-                    master_token: Some(new_token),
-                    clear_token: None,
-                };
-
-                if let Ok(json) = serde_json::to_string(&message) {
-                    let _ = client_info.sender.send(json);
-                }
+        println!("Promoting slave {} to master (room: {})", new_master_id, room_id);
+        state.metrics.master_promotions.inc();
+
+        // Update room state
+        room.master_client_id = Some(new_master_id);
+        room.master_token = Some(new_token.clone());
+
+        // Update client role and notify it
+        if let Some(client_info) = room.clients.get_mut(&new_master_id) {
+            client_info.role = ClientRole::Master;
+            state.metrics.track_role(&ClientRole::Slave, -1);
+            state.metrics.track_role(&ClientRole::Master, 1);
+
+            // Send role changed message
+            let message = Message::RoleChanged {
+                new_role: ClientRole::Master,
+                client_id: new_master_id,
+                master_client_id: Some(new_master_id),
+                master_token: Some(new_token),
+                clear_token: None,
+            };
+
+            if let Ok(json) = serde_json::to_string(&message) {
+                let _ = try_send_text(&client_info.sender, json);
             }
         }
+        persist_master_tokens(state);
     } else {
-        println!("No slaves available for promotion");
+        println!("No slaves available for promotion (room: {})", room_id);
+        drop(rooms);
+        drop_room_if_empty(state, room_id);
     }
 }
 
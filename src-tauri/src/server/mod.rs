@@ -2,15 +2,43 @@ pub mod state;
 pub mod protocol;
 pub mod handlers;
 pub mod persistence;
+pub mod db;
 
 pub use state::{AppState, ClientRole};
 pub use protocol::Message;
 
-use axum::{Router, routing::get};
+use axum::{
+    Router,
+    routing::get,
+    extract::State,
+    response::{IntoResponse, Response},
+    response::sse::{Event, KeepAlive, Sse},
+    http::header,
+};
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::Json;
+use futures::{Stream, StreamExt};
+use prometheus::{Encoder, TextEncoder};
+use serde::Deserialize;
+use tokio::fs;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tower_http::services::ServeDir;
 use std::sync::Arc;
 use std::path::PathBuf;
 
+use state::GameState;
+
+/// Snapshot of a room's board, used to seed and resync its SSE feed.
+fn room_snapshot(state: &Arc<AppState>, room_id: &str) -> Option<GameState> {
+    state
+        .rooms
+        .read()
+        .get(room_id)
+        .and_then(|room| room.load_state())
+}
+
 /// Get the base directory for resources (public/, config/, data/)
 fn get_resource_dir() -> PathBuf {
     #[cfg(debug_assertions)]
@@ -47,10 +75,24 @@ pub async fn start_server(state: Arc<AppState>, port: u16) -> Result<(), Box<dyn
 
     // Load persisted game state if it exists
     persistence::load_game_state(&state).await?;
+    // Reload master tokens so a restart doesn't orphan operator authority.
+    persistence::load_master_tokens(&state).await?;
+
+    // Open the SQLite event log and repopulate the latest snapshot per room.
+    let db_path = base_dir.join("data").join("events.db");
+    fs::create_dir_all(base_dir.join("data")).await?;
+    let pool = db::init_pool(&db_path.to_string_lossy()).await?;
+    db::load_latest_snapshots(&pool, &state).await?;
+    let _ = state.db.set(pool);
 
+    let shutdown_state = state.clone();
     let app = Router::new()
         .route("/ws", get(handlers::websocket::websocket_handler))
         .route("/init_data.json", get(handlers::init_data::init_data_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/events/:room", get(events_handler))
+        .route("/history/:room", get(history_handler))
+        .route("/state_at/:room", get(state_at_handler))
         .nest_service("/", ServeDir::new(public_dir))
         .with_state(state);
 
@@ -59,8 +101,144 @@ pub async fn start_server(state: Arc<AppState>, port: u16) -> Result<(), Box<dyn
 
     println!("Server running on http://127.0.0.1:{}", port);
 
+    // On shutdown, flush the latest game state and tell every connected
+    // client the broadcast has ended before the sockets are dropped.
+    let shutdown_signal = async move {
+        // Trigger on either an in-app close (the `shutdown` Notify) or a
+        // termination signal (Ctrl-C / SIGINT), whichever comes first.
+        tokio::select! {
+            _ = shutdown_state.shutdown.notified() => {}
+            _ = tokio::signal::ctrl_c() => {
+                println!("Received termination signal");
+            }
+        }
+        println!("Shutdown requested, flushing game state and notifying clients");
+
+        let room_ids: Vec<String> = shutdown_state.rooms.read().keys().cloned().collect();
+        for room_id in &room_ids {
+            if let Err(e) = persistence::save_game_state(&shutdown_state, room_id).await {
+                eprintln!("Failed to save game state on shutdown (room {}): {}", room_id, e);
+            }
+        }
+
+        if let Ok(json) = serde_json::to_string(&Message::ServerShutdown) {
+            for room in shutdown_state.rooms.read().values() {
+                for client in room.clients.values() {
+                    let _ = client.sender.try_send(axum::extract::ws::Message::Text(json.clone()));
+                }
+            }
+        }
+
+        // Give the per-connection send tasks a moment to drain the shutdown
+        // frame before the sockets are torn down.
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    };
+
     axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal)
         .await?;
 
     Ok(())
+}
+
+/// Handler for `/metrics`: encodes the Prometheus registry in text format.
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let encoder = TextEncoder::new();
+    let families = state.metrics.registry.gather();
+    let mut buf = Vec::new();
+    if let Err(e) = encoder.encode(&families, &mut buf) {
+        eprintln!("Failed to encode metrics: {}", e);
+    }
+    let body = String::from_utf8_lossy(&buf).into_owned();
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Handler for `/events/:room`: a read-only Server-Sent Events feed of a single
+/// room's `GameState` changes for lightweight viewers (e.g. an OBS browser
+/// source) that don't need the full WebSocket handshake/role protocol. The
+/// stream emits that room's current snapshot first, then every subsequent
+/// update for the same room; updates to other rooms are filtered out.
+async fn events_handler(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let initial = room_snapshot(&state, &room);
+    let rx = state.state_tx.subscribe();
+    let snapshot_src = state.clone();
+    let room_filter = room.clone();
+
+    let live = BroadcastStream::new(rx).filter_map(move |res| {
+        let snapshot_src = snapshot_src.clone();
+        let room_filter = room_filter.clone();
+        async move {
+            match res {
+                // Only forward updates belonging to this room.
+                Ok((room_id, game_state)) if room_id == room_filter => Some(game_state),
+                Ok(_) => None,
+                // A slow viewer that fell behind resyncs from the latest
+                // snapshot rather than tearing down the stream.
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                    eprintln!("SSE viewer lagged by {} updates, resyncing", skipped);
+                    room_snapshot(&snapshot_src, &room_filter)
+                }
+            }
+        }
+    });
+
+    let stream = futures::stream::iter(initial)
+        .chain(live)
+        .map(|game_state: GameState| Event::default().json_data(game_state));
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Query parameters for `/state_at/:room`.
+#[derive(Debug, Deserialize)]
+struct StateAtQuery {
+    /// RFC 3339 timestamp; the board is reconstructed as of this instant.
+    at: String,
+}
+
+/// Handler for `/history/:room`: the full, time-ordered update log for a board.
+async fn history_handler(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+) -> Response {
+    let Some(pool) = state.db.get() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "event log not ready").into_response();
+    };
+
+    match db::fetch_history(pool, &room).await {
+        Ok(history) => Json(history).into_response(),
+        Err(e) => {
+            eprintln!("Failed to fetch history for room {}: {}", room, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "failed to fetch history").into_response()
+        }
+    }
+}
+
+/// Handler for `/state_at/:room?at=<rfc3339>`: board state as of a timestamp.
+async fn state_at_handler(
+    State(state): State<Arc<AppState>>,
+    Path(room): Path<String>,
+    Query(query): Query<StateAtQuery>,
+) -> Response {
+    let Some(pool) = state.db.get() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "event log not ready").into_response();
+    };
+
+    let at = match chrono::DateTime::parse_from_rfc3339(&query.at) {
+        Ok(dt) => dt.with_timezone(&chrono::Utc),
+        Err(_) => return (StatusCode::BAD_REQUEST, "invalid 'at' timestamp").into_response(),
+    };
+
+    match db::state_as_of(pool, &room, at).await {
+        Ok(Some(game_state)) => Json(game_state).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "no state at that time").into_response(),
+        Err(e) => {
+            eprintln!("Failed to reconstruct state for room {}: {}", room, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "failed to reconstruct state").into_response()
+        }
+    }
 }
\ No newline at end of file
@@ -1,7 +1,29 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 use tokio::fs;
-use super::state::{AppState, GameState};
+use tokio::io::AsyncWriteExt;
+use super::state::{AppState, GameRoom, GameState, MasterToken, RoomId, TokenGracePeriod};
+
+/// One timestamped line of the append-only game journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub state: GameState,
+}
+
+/// Persisted master authority for one room, so a server restart mid-broadcast
+/// doesn't orphan the operator's token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedMaster {
+    pub token: MasterToken,
+    pub grace_expires_at: Option<DateTime<Utc>>,
+}
+
+/// How long a reloaded master token stays reclaimable after a restart.
+const MASTER_RECONNECT_GRACE_SECS: i64 = 60;
 
 /// Get the data directory path
 fn get_data_dir() -> PathBuf {
@@ -33,40 +55,216 @@ fn get_config_dir() -> PathBuf {
     }
 }
 
-/// Save game state to data/current_game.json
-pub async fn save_game_state(state: &Arc<AppState>) -> Result<(), std::io::Error> {
-    let game_state = state.game_state.read().clone();
+/// Directory holding one JSON snapshot per room.
+fn get_rooms_dir() -> PathBuf {
+    get_data_dir().join("rooms")
+}
+
+/// Save a single room's game state to data/rooms/<id>.json
+pub async fn save_game_state(state: &Arc<AppState>, room_id: &str) -> Result<(), std::io::Error> {
+    let game_state = state
+        .rooms
+        .read()
+        .get(room_id)
+        .and_then(|room| room.load_state());
 
     if let Some(game_state) = game_state {
-        let data_dir = get_data_dir();
+        let rooms_dir = get_rooms_dir();
 
-        // Create data directory if it doesn't exist
-        fs::create_dir_all(&data_dir).await?;
+        // Create rooms directory if it doesn't exist
+        fs::create_dir_all(&rooms_dir).await?;
 
-        let file_path = data_dir.join("current_game.json");
+        let file_path = rooms_dir.join(format!("{}.json", room_id));
         let json = serde_json::to_string_pretty(&game_state)
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
 
         fs::write(file_path, json).await?;
-        println!("Game state saved successfully");
+        println!("Game state saved successfully (room: {})", room_id);
     }
 
     Ok(())
 }
 
-/// Load game state from data/current_game.json
+/// Load every persisted room from data/rooms/*.json into `AppState`.
 pub async fn load_game_state(state: &Arc<AppState>) -> Result<(), Box<dyn std::error::Error>> {
-    let data_dir = get_data_dir();
-    let file_path = data_dir.join("current_game.json");
+    let rooms_dir = get_rooms_dir();
 
-    if file_path.exists() {
-        let contents = fs::read_to_string(&file_path).await?;
+    if !rooms_dir.exists() {
+        println!("No saved rooms found");
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(&rooms_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(room_id) = path.file_stem().and_then(|s| s.to_str()).map(String::from) else {
+            continue;
+        };
+
+        let contents = fs::read_to_string(&path).await?;
         let game_state: GameState = serde_json::from_str(&contents)?;
 
-        *state.game_state.write() = Some(game_state);
-        println!("Game state loaded from {:?}", file_path);
-    } else {
-        println!("No saved game state found");
+        let mut rooms = state.rooms.write();
+        let room = rooms.entry(room_id.clone()).or_insert_with(GameRoom::new);
+        room.store_state(&game_state);
+        println!("Game state loaded from {:?} (room: {})", path, room_id);
+    }
+
+    // Replay each room's journal so undo/redo history survives a restart. The
+    // journal's last entry is the authoritative latest state; the cursor sits
+    // at the end.
+    let data_dir = get_data_dir();
+    if data_dir.exists() {
+        let mut entries = fs::read_dir(&data_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(room_id) = path.file_stem().and_then(|s| s.to_str()).map(String::from) else {
+                continue;
+            };
+
+            let journal = load_journal(&room_id).await?;
+            if journal.is_empty() {
+                continue;
+            }
+
+            let mut rooms = state.rooms.write();
+            let room = rooms.entry(room_id.clone()).or_insert_with(GameRoom::new);
+            room.cursor = journal.len() - 1;
+            if let Some(last) = journal.last() { room.store_state(last); }
+            room.journal = journal;
+            println!("Journal replayed for room {} ({} entries)", room_id, room.journal.len());
+        }
+    }
+
+    Ok(())
+}
+
+/// Path to a room's append-only journal at data/<room>.jsonl
+fn journal_path(room_id: &str) -> PathBuf {
+    get_data_dir().join(format!("{}.jsonl", room_id))
+}
+
+/// Append a single board state to a room's journal as a timestamped JSONL line.
+pub async fn append_journal(room_id: &str, state: &GameState) -> Result<(), std::io::Error> {
+    let data_dir = get_data_dir();
+    fs::create_dir_all(&data_dir).await?;
+
+    let entry = JournalEntry {
+        timestamp: Utc::now(),
+        state: state.clone(),
+    };
+    let mut line = serde_json::to_string(&entry)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    line.push('\n');
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_path(room_id))
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Rewrite a room's journal to exactly `states`, used when an undo followed
+/// by a fresh update truncates the redo-able tail so the file stays linear.
+pub async fn rewrite_journal(room_id: &str, states: &[GameState]) -> Result<(), std::io::Error> {
+    let data_dir = get_data_dir();
+    fs::create_dir_all(&data_dir).await?;
+
+    let mut buf = String::new();
+    for state in states {
+        let entry = JournalEntry {
+            timestamp: Utc::now(),
+            state: state.clone(),
+        };
+        let line = serde_json::to_string(&entry)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        buf.push_str(&line);
+        buf.push('\n');
+    }
+
+    fs::write(journal_path(room_id), buf).await?;
+    Ok(())
+}
+
+/// Read a room's journal from disk, oldest entry first.
+pub async fn load_journal(room_id: &str) -> Result<Vec<GameState>, Box<dyn std::error::Error>> {
+    let path = journal_path(room_id);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(&path).await?;
+    let mut states = Vec::new();
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: JournalEntry = serde_json::from_str(line)?;
+        states.push(entry.state);
+    }
+    Ok(states)
+}
+
+/// Path to the persisted master-token file.
+fn master_path() -> PathBuf {
+    get_data_dir().join("master.json")
+}
+
+/// Persist every room's active master token (and grace period) to data/master.json.
+pub async fn save_master_tokens(state: &Arc<AppState>) -> Result<(), std::io::Error> {
+    let tokens: HashMap<RoomId, PersistedMaster> = state
+        .rooms
+        .read()
+        .iter()
+        .filter_map(|(id, room)| {
+            room.master_token.clone().map(|token| {
+                (
+                    id.clone(),
+                    PersistedMaster {
+                        token,
+                        grace_expires_at: room.master_token_grace.as_ref().map(|g| g.expires_at),
+                    },
+                )
+            })
+        })
+        .collect();
+
+    let data_dir = get_data_dir();
+    fs::create_dir_all(&data_dir).await?;
+    let json = serde_json::to_string_pretty(&tokens)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    fs::write(master_path(), json).await?;
+    Ok(())
+}
+
+/// Reload persisted master tokens, granting each a fresh reconnection grace
+/// window so the previous operator can reclaim the master role after a restart.
+pub async fn load_master_tokens(state: &Arc<AppState>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = master_path();
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let contents = fs::read_to_string(&path).await?;
+    let tokens: HashMap<RoomId, PersistedMaster> = serde_json::from_str(&contents)?;
+
+    let mut rooms = state.rooms.write();
+    for (room_id, persisted) in tokens {
+        let room = rooms.entry(room_id.clone()).or_insert_with(GameRoom::new);
+        room.master_token = Some(persisted.token.clone());
+        room.master_token_grace = Some(TokenGracePeriod {
+            token: persisted.token,
+            expires_at: Utc::now() + Duration::seconds(MASTER_RECONNECT_GRACE_SECS),
+        });
+        println!("Restored master token for room {} (reclaimable for {}s)", room_id, MASTER_RECONNECT_GRACE_SECS);
     }
 
     Ok(())
@@ -87,14 +285,9 @@ pub async fn load_init_config() -> Result<serde_json::Value, Box<dyn std::error:
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::TempDir;
 
-    #[tokio::test]
-    async fn test_save_and_load_game_state() {
-        let temp_dir = TempDir::new().unwrap();
-        let _temp_path = temp_dir.path().to_path_buf();
-
-        // Create test game state
+    #[test]
+    fn test_persisted_state_round_trips_through_a_room() {
         let state = Arc::new(AppState::new());
         let test_game_state = GameState {
             game_inning: 5,
@@ -113,9 +306,43 @@ mod tests {
             last_inning: 9,
         };
 
-        *state.game_state.write() = Some(test_game_state.clone());
+        let mut room = GameRoom::new();
+        room.store_state(&test_game_state);
+        state.rooms.write().insert("default".to_string(), room);
+
+        // The stored board is registered and reloads identically.
+        let loaded = state
+            .rooms
+            .read()
+            .get("default")
+            .and_then(|r| r.load_state());
+        assert_eq!(loaded, Some(test_game_state));
+    }
+
+    #[test]
+    fn test_journal_entry_serialization_round_trips() {
+        let entry = JournalEntry {
+            timestamp: Utc::now(),
+            state: GameState {
+                game_inning: 1,
+                top: false,
+                first_base: true,
+                second_base: false,
+                third_base: false,
+                ball_cnt: 1,
+                strike_cnt: 2,
+                out_cnt: 1,
+                score_top: 0,
+                score_bottom: 0,
+                game_title: "J".to_string(),
+                team_top: "X".to_string(),
+                team_bottom: "Y".to_string(),
+                last_inning: 7,
+            },
+        };
 
-        // Note: In actual tests, we'd need to mock get_data_dir() to use temp_path
-        // For now, this test verifies the logic compiles correctly
+        let line = serde_json::to_string(&entry).unwrap();
+        let parsed: JournalEntry = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed.state, entry.state);
     }
 }
\ No newline at end of file
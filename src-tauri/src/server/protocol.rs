@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use super::state::{ClientRole, GameState, MasterToken};
+use super::state::{ClientRole, GameState, GameStateDelta, MasterToken, RoomId};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -10,6 +10,12 @@ pub enum Message {
         client_type: String,
         #[serde(rename = "masterToken")]
         master_token: Option<MasterToken>,
+        #[serde(rename = "roomId")]
+        #[serde(default)]
+        room_id: Option<RoomId>,
+        #[serde(rename = "credential")]
+        #[serde(default)]
+        credential: Option<String>,
     },
 
     #[serde(rename = "role_assignment")]
@@ -21,6 +27,8 @@ pub enum Message {
         master_client_id: Option<u64>,
         #[serde(rename = "masterToken")]
         master_token: Option<MasterToken>,
+        #[serde(rename = "roomId")]
+        room_id: RoomId,
     },
 
     #[serde(rename = "role_changed")]
@@ -47,10 +55,40 @@ pub enum Message {
     GameStateBroadcast {
         #[serde(rename = "boardData")]
         board_data: GameState,
+        version: u64,
     },
 
+    #[serde(rename = "game_state_delta")]
+    GameStateDelta {
+        changes: GameStateDelta,
+        version: u64,
+    },
+
+    #[serde(rename = "request_resync")]
+    RequestResync,
+
     #[serde(rename = "release_master")]
     ReleaseMaster,
+
+    #[serde(rename = "undo")]
+    Undo,
+
+    #[serde(rename = "redo")]
+    Redo,
+
+    #[serde(rename = "server_info")]
+    ServerInfo {
+        version: String,
+        capabilities: Vec<String>,
+    },
+
+    #[serde(rename = "server_shutdown")]
+    ServerShutdown,
+
+    #[serde(rename = "error")]
+    ErrorResponse {
+        message: String,
+    },
 }
 
 #[cfg(test)]
@@ -62,6 +100,8 @@ mod tests {
         let msg = Message::Handshake {
             client_type: "operation".to_string(),
             master_token: Some("test-token-123".to_string()),
+            room_id: None,
+            credential: None,
         };
 
         let json = serde_json::to_string(&msg).unwrap();
@@ -80,7 +120,7 @@ mod tests {
 
         let msg: Message = serde_json::from_str(json).unwrap();
         match msg {
-            Message::Handshake { client_type, master_token } => {
+            Message::Handshake { client_type, master_token, .. } => {
                 assert_eq!(client_type, "operation");
                 assert_eq!(master_token, Some("test-token-123".to_string()));
             }
@@ -95,6 +135,7 @@ mod tests {
             client_id: 123,
             master_client_id: Some(123),
             master_token: Some("master-token".to_string()),
+            room_id: "default".to_string(),
         };
 
         let json = serde_json::to_string(&msg).unwrap();
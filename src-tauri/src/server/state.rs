@@ -1,13 +1,26 @@
 use parking_lot::RwLock;
 use std::collections::HashMap;
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use prometheus::{IntCounter, IntGauge, IntGaugeVec, Opts, Registry};
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc::UnboundedSender;
+use axum::extract::ws::Message as WsMessage;
+use tokio::sync::Notify;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::Sender;
+
+/// Capacity of each client's outbound queue. A viewer whose socket stalls and
+/// lets this fill is considered too far behind and is dropped.
+pub const CLIENT_CHANNEL_CAPACITY: usize = 200;
 
 pub type ClientId = u64;
 pub type MasterToken = String;
+pub type RoomId = String;
+
+/// Room id assigned to clients that handshake without an explicit `roomId`,
+/// preserving the single-game behaviour for older clients.
+pub const DEFAULT_ROOM: &str = "default";
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -22,8 +35,9 @@ pub struct ClientInfo {
     pub id: ClientId,
     pub role: ClientRole,
     pub client_type: String,
+    pub room_id: RoomId,
     pub connected_at: DateTime<Utc>,
-    pub sender: UnboundedSender<String>,
+    pub sender: Sender<WsMessage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -44,30 +58,390 @@ pub struct GameState {
     pub last_inning: i32,
 }
 
+/// Field-level diff between two [`GameState`]s. Only fields that actually
+/// changed are `Some`; the rest are omitted from the wire form so a delta
+/// carries just what moved. Paired with a version number this lets already
+/// synced clients patch their board instead of replacing it wholesale.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameStateDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_inning: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_base: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second_base: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub third_base: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ball_cnt: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strike_cnt: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_cnt: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_top: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_bottom: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team_top: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team_bottom: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_inning: Option<i32>,
+}
+
+impl GameStateDelta {
+    /// Compute the changed fields going from `prev` to `next`.
+    pub fn between(prev: &GameState, next: &GameState) -> Self {
+        macro_rules! diff {
+            ($field:ident) => {
+                if prev.$field != next.$field {
+                    Some(next.$field.clone())
+                } else {
+                    None
+                }
+            };
+        }
+        GameStateDelta {
+            game_inning: diff!(game_inning),
+            top: diff!(top),
+            first_base: diff!(first_base),
+            second_base: diff!(second_base),
+            third_base: diff!(third_base),
+            ball_cnt: diff!(ball_cnt),
+            strike_cnt: diff!(strike_cnt),
+            out_cnt: diff!(out_cnt),
+            score_top: diff!(score_top),
+            score_bottom: diff!(score_bottom),
+            game_title: diff!(game_title),
+            team_top: diff!(team_top),
+            team_bottom: diff!(team_bottom),
+            last_inning: diff!(last_inning),
+        }
+    }
+
+    /// Whether no field changed (the delta would carry nothing).
+    pub fn is_empty(&self) -> bool {
+        *self == GameStateDelta::default()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TokenGracePeriod {
     pub token: MasterToken,
     pub expires_at: DateTime<Utc>,
 }
 
+/// Prometheus collectors exported on `/metrics` so operators can watch a
+/// broadcast session in Grafana. The handlers update these as clients come
+/// and go and as the master pushes state.
+pub struct Metrics {
+    pub registry: Registry,
+    /// Total currently-connected clients across all roles.
+    pub connected_total: IntGauge,
+    /// Currently-connected clients, labelled by role (`master`/`slave`/`viewer`).
+    pub connected_clients: IntGaugeVec,
+    /// Total `game_state_update` messages accepted from the master.
+    pub game_state_updates: IntCounter,
+    /// Total board broadcasts (full `GameStateBroadcast` or `GameStateDelta`)
+    /// fanned out to clients.
+    pub broadcasts: IntCounter,
+    /// Total master-token rotations (release_master plus grace-period promotions).
+    pub master_rotations: IntCounter,
+    /// Total slaves promoted to master.
+    pub master_promotions: IntCounter,
+    /// Total grace periods that expired without the master reconnecting.
+    pub grace_expirations: IntCounter,
+    /// The inning currently shown on the board.
+    pub current_inning: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let connected_total = IntGauge::new("bcb_connected_total", "Currently connected clients")
+            .expect("valid metric definition");
+        let connected_clients = IntGaugeVec::new(
+            Opts::new("bcb_connected_clients", "Currently connected clients by role"),
+            &["role"],
+        )
+        .expect("valid metric definition");
+        let game_state_updates = IntCounter::new(
+            "bcb_game_state_updates_total",
+            "Game state updates received from the master",
+        )
+        .expect("valid metric definition");
+        let broadcasts = IntCounter::new(
+            "bcb_broadcasts_total",
+            "Board broadcasts (full state or delta) fanned out to clients",
+        )
+        .expect("valid metric definition");
+        let master_rotations = IntCounter::new(
+            "bcb_master_rotations_total",
+            "Master-token rotations (release and grace-period promotions)",
+        )
+        .expect("valid metric definition");
+        let master_promotions = IntCounter::new(
+            "bcb_master_promotions_total",
+            "Slaves promoted to master",
+        )
+        .expect("valid metric definition");
+        let grace_expirations = IntCounter::new(
+            "bcb_grace_expirations_total",
+            "Grace periods that expired without reconnection",
+        )
+        .expect("valid metric definition");
+        let current_inning = IntGauge::new("bcb_current_inning", "Current game inning")
+            .expect("valid metric definition");
+
+        for collector in [
+            Box::new(connected_total.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(connected_clients.clone()),
+            Box::new(game_state_updates.clone()),
+            Box::new(broadcasts.clone()),
+            Box::new(master_rotations.clone()),
+            Box::new(master_promotions.clone()),
+            Box::new(grace_expirations.clone()),
+            Box::new(current_inning.clone()),
+        ] {
+            registry.register(collector).expect("metric not already registered");
+        }
+
+        Self {
+            registry,
+            connected_total,
+            connected_clients,
+            game_state_updates,
+            broadcasts,
+            master_rotations,
+            master_promotions,
+            grace_expirations,
+            current_inning,
+        }
+    }
+
+    /// Adjust the connected-client gauge for a given role.
+    pub fn track_role(&self, role: &ClientRole, delta: i64) {
+        self.connected_clients
+            .with_label_values(&[role.as_metric_label()])
+            .add(delta);
+    }
+}
+
+impl ClientRole {
+    /// Lower-case label used for the `role` dimension of the metrics.
+    pub fn as_metric_label(&self) -> &'static str {
+        match self {
+            ClientRole::Master => "master",
+            ClientRole::Slave => "slave",
+            ClientRole::Viewer => "viewer",
+        }
+    }
+}
+
+/// The frequently-touched scoreboard counters, kept as lock-free atomics so a
+/// ball/strike/out increment doesn't contend with readers reconstructing the
+/// whole board. `has_state` distinguishes "never set" from an all-zero board.
+#[derive(Default)]
+pub struct GameCounters {
+    pub has_state: AtomicBool,
+    pub game_inning: AtomicI32,
+    pub top: AtomicBool,
+    pub ball_cnt: AtomicI32,
+    pub strike_cnt: AtomicI32,
+    pub out_cnt: AtomicI32,
+    pub score_top: AtomicI32,
+    pub score_bottom: AtomicI32,
+}
+
+/// The remaining board fields that change less often than the counters and so
+/// stay behind a single lock.
+#[derive(Debug, Clone, Default)]
+pub struct GameMeta {
+    pub first_base: bool,
+    pub second_base: bool,
+    pub third_base: bool,
+    pub game_title: String,
+    pub team_top: String,
+    pub team_bottom: String,
+    pub last_inning: i32,
+}
+
+/// A single broadcast, owning its own board, master election and client set.
+/// One server instance hosts several of these concurrently (e.g. two fields
+/// of a tournament), keyed by [`RoomId`].
+#[derive(Default)]
+pub struct GameRoom {
+    pub counters: GameCounters,
+    pub meta: RwLock<GameMeta>,
+    pub master_client_id: Option<ClientId>,
+    pub master_token: Option<MasterToken>,
+    pub master_token_grace: Option<TokenGracePeriod>,
+    pub clients: HashMap<ClientId, ClientInfo>,
+    /// Append-only history of every board state the master has pushed, used
+    /// for undo/redo. `cursor` is the index of the state currently shown;
+    /// entries after it are redo-able until a fresh update truncates them.
+    pub journal: Vec<GameState>,
+    pub cursor: usize,
+    /// Monotonically increasing revision of this room's board. Every accepted
+    /// update (and every undo/redo jump) bumps it; it rides along on both the
+    /// full `GameStateBroadcast` and each `GameStateDelta` so a client can
+    /// detect a missed revision and ask for a full resync.
+    pub version: u64,
+}
+
+impl GameRoom {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reconstruct the full board from the atomics plus the meta lock, or
+    /// `None` if no state has been pushed yet.
+    pub fn load_state(&self) -> Option<GameState> {
+        if !self.counters.has_state.load(Ordering::Acquire) {
+            return None;
+        }
+        let meta = self.meta.read();
+        Some(GameState {
+            game_inning: self.counters.game_inning.load(Ordering::Relaxed),
+            top: self.counters.top.load(Ordering::Relaxed),
+            first_base: meta.first_base,
+            second_base: meta.second_base,
+            third_base: meta.third_base,
+            ball_cnt: self.counters.ball_cnt.load(Ordering::Relaxed),
+            strike_cnt: self.counters.strike_cnt.load(Ordering::Relaxed),
+            out_cnt: self.counters.out_cnt.load(Ordering::Relaxed),
+            score_top: self.counters.score_top.load(Ordering::Relaxed),
+            score_bottom: self.counters.score_bottom.load(Ordering::Relaxed),
+            game_title: meta.game_title.clone(),
+            team_top: meta.team_top.clone(),
+            team_bottom: meta.team_bottom.clone(),
+            last_inning: meta.last_inning,
+        })
+    }
+
+    /// Store a full board, splitting the counters into the atomics and the
+    /// rest into the meta lock. Only needs a shared borrow of the room.
+    pub fn store_state(&self, gs: &GameState) {
+        self.counters.game_inning.store(gs.game_inning, Ordering::Relaxed);
+        self.counters.top.store(gs.top, Ordering::Relaxed);
+        self.counters.ball_cnt.store(gs.ball_cnt, Ordering::Relaxed);
+        self.counters.strike_cnt.store(gs.strike_cnt, Ordering::Relaxed);
+        self.counters.out_cnt.store(gs.out_cnt, Ordering::Relaxed);
+        self.counters.score_top.store(gs.score_top, Ordering::Relaxed);
+        self.counters.score_bottom.store(gs.score_bottom, Ordering::Relaxed);
+        {
+            let mut meta = self.meta.write();
+            meta.first_base = gs.first_base;
+            meta.second_base = gs.second_base;
+            meta.third_base = gs.third_base;
+            meta.game_title = gs.game_title.clone();
+            meta.team_top = gs.team_top.clone();
+            meta.team_bottom = gs.team_bottom.clone();
+            meta.last_inning = gs.last_inning;
+        }
+        self.counters.has_state.store(true, Ordering::Release);
+    }
+
+    /// Record a fresh board in the journal: drop any redo-able tail ahead of
+    /// the cursor, append the new state and point the cursor at it. Returns
+    /// whether a redo tail was dropped (so the caller knows the journal file
+    /// must be rewritten rather than appended to).
+    pub fn push_journal(&mut self, gs: &GameState) -> bool {
+        let had_redo = !self.journal.is_empty() && self.cursor + 1 < self.journal.len();
+        if had_redo {
+            self.journal.truncate(self.cursor + 1);
+        }
+        self.journal.push(gs.clone());
+        self.cursor = self.journal.len() - 1;
+        had_redo
+    }
+
+    /// Step the cursor one entry back, returning the board now shown, or
+    /// `None` when already at the oldest entry.
+    pub fn undo(&mut self) -> Option<GameState> {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            Some(self.journal[self.cursor].clone())
+        } else {
+            None
+        }
+    }
+
+    /// Step the cursor one entry forward, returning the board now shown, or
+    /// `None` when already at the newest entry.
+    pub fn redo(&mut self) -> Option<GameState> {
+        if self.cursor + 1 < self.journal.len() {
+            self.cursor += 1;
+            Some(self.journal[self.cursor].clone())
+        } else {
+            None
+        }
+    }
+}
+
+/// All live game rooms keyed by id. One server instance hosts several
+/// simultaneous broadcasts (e.g. several fields of a tournament).
+pub type RoomRegistry = HashMap<RoomId, GameRoom>;
+
 pub struct AppState {
-    pub clients: RwLock<HashMap<ClientId, ClientInfo>>,
-    pub game_state: RwLock<Option<GameState>>,
-    pub master_client_id: RwLock<Option<ClientId>>,
-    pub master_token: RwLock<Option<MasterToken>>,
-    pub master_token_grace: RwLock<Option<TokenGracePeriod>>,
+    pub rooms: RwLock<RoomRegistry>,
     pub client_counter: AtomicU64,
+    pub metrics: Metrics,
+    /// Fan-out channel for read-only viewers (e.g. the `/events/:room` SSE
+    /// feed). Every accepted `GameStateUpdate` is published here, tagged with
+    /// its `RoomId`, so a feed can filter to a single room and not leak other
+    /// rooms' boards.
+    pub state_tx: broadcast::Sender<(RoomId, GameState)>,
+    /// Signalled once when the application is closing so `start_server` can
+    /// run its graceful-shutdown path (flush state, notify clients).
+    pub shutdown: Notify,
+    /// SQLite event-log pool, initialised once in `start_server`.
+    pub db: tokio::sync::OnceCell<sqlx::SqlitePool>,
+    /// SHA3 hash of the operator secret required to claim an operation role.
+    /// `None` disables auth (anyone may operate), preserving earlier behaviour.
+    pub operator_secret_hash: Option<String>,
+}
+
+/// SHA3-256 hash of a string, hex-encoded, used to check the operator secret.
+pub fn sha3_hex(input: &str) -> String {
+    use sha3::{Digest, Sha3_256};
+    let mut hasher = Sha3_256::new();
+    hasher.update(input.as_bytes());
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Read the operator secret from the `BROADCAST_OPERATOR_SECRET` environment
+/// variable and return its hash, or `None` when unset.
+fn load_operator_secret_hash() -> Option<String> {
+    match std::env::var("BROADCAST_OPERATOR_SECRET") {
+        Ok(s) if !s.is_empty() => Some(sha3_hex(&s)),
+        _ => {
+            eprintln!(
+                "WARNING: BROADCAST_OPERATOR_SECRET is not set; operator \
+                 authentication is DISABLED and anyone who reaches the socket \
+                 can claim the master role. Set it to require a credential."
+            );
+            None
+        }
+    }
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
-            clients: RwLock::new(HashMap::new()),
-            game_state: RwLock::new(None),
-            master_client_id: RwLock::new(None),
-            master_token: RwLock::new(None),
-            master_token_grace: RwLock::new(None),
+            rooms: RwLock::new(HashMap::new()),
             client_counter: AtomicU64::new(1),
+            metrics: Metrics::new(),
+            state_tx: broadcast::channel(128).0,
+            shutdown: Notify::new(),
+            db: tokio::sync::OnceCell::new(),
+            operator_secret_hash: load_operator_secret_hash(),
         }
     }
 
@@ -89,10 +463,7 @@ mod tests {
     #[test]
     fn test_app_state_initialization() {
         let state = AppState::new();
-        assert!(state.clients.read().is_empty());
-        assert!(state.game_state.read().is_none());
-        assert!(state.master_client_id.read().is_none());
-        assert!(state.master_token.read().is_none());
+        assert!(state.rooms.read().is_empty());
         assert_eq!(state.client_counter.load(std::sync::atomic::Ordering::SeqCst), 1);
     }
 
@@ -151,4 +522,115 @@ mod tests {
 
         assert_eq!(game_state, deserialized);
     }
+
+    fn sample_state() -> GameState {
+        GameState {
+            game_inning: 5,
+            top: true,
+            first_base: false,
+            second_base: true,
+            third_base: false,
+            ball_cnt: 2,
+            strike_cnt: 1,
+            out_cnt: 0,
+            score_top: 3,
+            score_bottom: 2,
+            game_title: "夏季大会".to_string(),
+            team_top: "横浜M".to_string(),
+            team_bottom: "静岡D".to_string(),
+            last_inning: 9,
+        }
+    }
+
+    #[test]
+    fn test_delta_between_identical_is_empty() {
+        let a = sample_state();
+        let delta = GameStateDelta::between(&a, &a);
+        assert!(delta.is_empty());
+        assert_eq!(delta, GameStateDelta::default());
+    }
+
+    #[test]
+    fn test_delta_between_reports_only_changed_fields() {
+        let prev = sample_state();
+        let mut next = prev.clone();
+        next.ball_cnt = 3;
+        next.first_base = true;
+
+        let delta = GameStateDelta::between(&prev, &next);
+        assert!(!delta.is_empty());
+        assert_eq!(delta.ball_cnt, Some(3));
+        assert_eq!(delta.first_base, Some(true));
+        // Unchanged fields stay absent from the delta.
+        assert_eq!(delta.strike_cnt, None);
+        assert_eq!(delta.score_top, None);
+        assert_eq!(delta.game_title, None);
+    }
+
+    #[test]
+    fn test_delta_serializes_only_changed_fields() {
+        let prev = sample_state();
+        let mut next = prev.clone();
+        next.score_top = 4;
+
+        let delta = GameStateDelta::between(&prev, &next);
+        let json = serde_json::to_string(&delta).unwrap();
+        assert_eq!(json, "{\"score_top\":4}");
+    }
+
+    #[test]
+    fn test_journal_undo_redo_cursor() {
+        let mut room = GameRoom::new();
+        let mut s1 = sample_state();
+        let mut s2 = sample_state();
+        s2.score_top = 1;
+        let mut s3 = sample_state();
+        s3.score_top = 2;
+
+        // Record three states; the cursor tracks the newest.
+        assert!(!room.push_journal(&s1));
+        assert!(!room.push_journal(&s2));
+        assert!(!room.push_journal(&s3));
+        assert_eq!(room.cursor, 2);
+        assert_eq!(room.journal.len(), 3);
+
+        // Undo twice, then redo once.
+        assert_eq!(room.undo(), Some(s2.clone()));
+        assert_eq!(room.cursor, 1);
+        assert_eq!(room.undo(), Some(s1.clone()));
+        assert_eq!(room.cursor, 0);
+        assert_eq!(room.undo(), None); // already at the oldest
+        assert_eq!(room.cursor, 0);
+        assert_eq!(room.redo(), Some(s2.clone()));
+        assert_eq!(room.cursor, 1);
+    }
+
+    #[test]
+    fn test_journal_push_after_undo_truncates_redo_tail() {
+        let mut room = GameRoom::new();
+        let s1 = sample_state();
+        let mut s2 = sample_state();
+        s2.score_top = 1;
+        let mut s3 = sample_state();
+        s3.score_top = 2;
+
+        room.push_journal(&s1);
+        room.push_journal(&s2);
+        room.push_journal(&s3);
+
+        // Go back to s1, then push a fresh state: the redo tail (s2, s3) is
+        // dropped so the journal stays linear.
+        room.undo();
+        room.undo();
+        assert_eq!(room.cursor, 0);
+
+        let mut s4 = sample_state();
+        s4.score_top = 9;
+        let truncated = room.push_journal(&s4);
+        assert!(truncated);
+        assert_eq!(room.journal.len(), 2);
+        assert_eq!(room.cursor, 1);
+        assert_eq!(room.journal[1].score_top, 9);
+        assert_eq!(room.redo(), None); // nothing ahead of the new tip
+    }
 }
\ No newline at end of file